@@ -0,0 +1,25 @@
+pub mod query;
+pub mod synonyms;
+
+use synonyms::SynonymTable;
+use std::time::Duration;
+
+/// Shared state handed to every route: where to reach Elasticsearch, the
+/// ceiling on how long a query is allowed to run, and the synonym table
+/// used to expand query tokens. Built once at startup so each request
+/// doesn't have to reload the synonym file from disk.
+pub struct Context {
+    pub es_cnx_string: String,
+    pub max_es_timeout: Duration,
+    pub synonyms: SynonymTable,
+}
+
+impl Context {
+    pub fn new(es_cnx_string: String, max_es_timeout: Duration) -> Self {
+        Context {
+            es_cnx_string,
+            max_es_timeout,
+            synonyms: synonyms::load(),
+        }
+    }
+}
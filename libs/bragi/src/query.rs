@@ -0,0 +1,276 @@
+use crate::model::BragiError;
+use crate::routes::autocomplete::Fuzziness;
+use crate::synonyms::SynonymTable;
+use mimir::objects::Coord;
+use serde_json::{json, Value};
+use std::time::Duration;
+
+/// Translate a word's `fuzziness`/`prefix_length` config into the ES clause
+/// fragment: below `min_length_1_typo` a word must match exactly (no
+/// `fuzziness` key at all), from `min_length_1_typo` up to (but not
+/// including) `min_length_2_typos` it tolerates one edit, and from
+/// `min_length_2_typos` on, two.
+fn fuzziness_for_word(word: &str, fuzziness: &Fuzziness) -> Option<(&'static str, u8)> {
+    let len = word.chars().count() as u8;
+    if len >= fuzziness.min_length_2_typos {
+        Some(("2", fuzziness.prefix_length))
+    } else if len >= fuzziness.min_length_1_typo {
+        Some(("1", fuzziness.prefix_length))
+    } else {
+        None
+    }
+}
+
+/// Build the `should` clauses for a single synonym-group member of a query
+/// token: the exact match, boosted so clean queries keep ranking above
+/// fuzzy ones, plus a fuzzy `fuzziness`/`prefix_length` match when
+/// `fuzziness` applies to a word this long. `token` is the original word
+/// typed by the user; `variant` is one expansion of it (itself included).
+fn variant_clauses(token: &str, variant: &str, fuzziness: Option<&Fuzziness>) -> Vec<Value> {
+    let mut clauses = vec![json!({
+        "match": {
+            "full_label": {
+                "query": variant,
+                "boost": if variant == token { 2.0 } else { 1.0 },
+            }
+        }
+    })];
+    if let Some(fuzziness) = fuzziness {
+        if let Some((edit_distance, prefix_length)) = fuzziness_for_word(variant, fuzziness) {
+            // an exact match also scores on the exact clause above, so it
+            // still outranks a document that only matches this fuzzy one
+            clauses.push(json!({
+                "match": {
+                    "full_label": {
+                        "query": variant,
+                        "fuzziness": edit_distance,
+                        "prefix_length": prefix_length,
+                        "boost": 1.0,
+                    }
+                }
+            }));
+        }
+    }
+    clauses
+}
+
+/// Build the `should` clauses for a single query token, expanded through
+/// its synonym group for `lang` (e.g. "bd" also matches "boulevard").
+fn token_clauses(
+    token: &str,
+    lang: &str,
+    fuzziness: Option<&Fuzziness>,
+    synonyms: &SynonymTable,
+) -> Vec<Value> {
+    synonyms
+        .expand(token, lang)
+        .iter()
+        .flat_map(|variant| variant_clauses(token, variant, fuzziness))
+        .collect()
+}
+
+/// Build the Elasticsearch query for `q`; each token is expanded through
+/// its synonym group for the query's (first) language, and when
+/// `fuzziness` is `Some`, longer words tolerate edit-distance typos.
+fn build_query(
+    q: &str,
+    langs: &[&str],
+    fuzziness: Option<&Fuzziness>,
+    synonyms: &SynonymTable,
+) -> Value {
+    let lang = langs.first().copied().unwrap_or("fr");
+    let should: Vec<Value> = q
+        .split_whitespace()
+        .flat_map(|token| token_clauses(token, lang, fuzziness, synonyms))
+        .collect();
+    json!({
+        "bool": {
+            "should": should,
+            "minimum_should_match": 1,
+        }
+    })
+}
+
+/// Restrict results to the given `_type` values (`house`, `street`, ...);
+/// no filter at all when `types` is empty, i.e. every type is accepted.
+fn type_filter(types: &[&str]) -> Option<Value> {
+    if types.is_empty() {
+        None
+    } else {
+        Some(json!({ "terms": { "type": types } }))
+    }
+}
+
+/// Restrict public-transport documents to the requested `pt_datasets`,
+/// unless `all_data` asks for every dataset. Documents with no `coverages`
+/// field at all (addresses, streets, ...) are never scoped by dataset and
+/// always pass through.
+fn dataset_filter(pt_datasets: &[&str], all_data: bool) -> Option<Value> {
+    if all_data || pt_datasets.is_empty() {
+        None
+    } else {
+        Some(json!({
+            "bool": {
+                "should": [
+                    { "terms": { "coverages": pt_datasets } },
+                    { "bool": { "must_not": { "exists": { "field": "coverages" } } } }
+                ],
+                "minimum_should_match": 1,
+            }
+        }))
+    }
+}
+
+/// Restrict results to those whose `coord` falls within `shape`, a single
+/// polygon given as `(lat, lon)` vertices.
+fn shape_filter(shape: &[(f64, f64)]) -> Value {
+    let coordinates: Vec<Vec<f64>> = shape.iter().map(|&(lat, lon)| vec![lon, lat]).collect();
+    json!({
+        "geo_shape": {
+            "coord": {
+                "shape": {
+                    "type": "polygon",
+                    "coordinates": [coordinates],
+                }
+            }
+        }
+    })
+}
+
+/// Boost (without excluding) results close to `coord`, so a user's position
+/// ranks nearby matches above distant ones of otherwise equal relevance.
+fn with_proximity(query: Value, coord: &Coord) -> Value {
+    json!({
+        "function_score": {
+            "query": query,
+            "functions": [{
+                "gauss": {
+                    "coord": {
+                        "origin": { "lat": coord.lat(), "lon": coord.lon() },
+                        "scale": "50km",
+                    }
+                }
+            }],
+            "score_mode": "max",
+            "boost_mode": "multiply",
+        }
+    })
+}
+
+#[allow(clippy::too_many_arguments)]
+fn build_body(
+    q: &str,
+    langs: &[&str],
+    fuzziness: Option<&Fuzziness>,
+    synonyms: &SynonymTable,
+    pt_datasets: &[&str],
+    all_data: bool,
+    types: &[&str],
+    coord: Option<&Coord>,
+    shape: Option<&[(f64, f64)]>,
+    offset: u64,
+    limit: u64,
+) -> Value {
+    let mut query = build_query(q, langs, fuzziness, synonyms);
+
+    let mut filters: Vec<Value> = Vec::new();
+    filters.extend(type_filter(types));
+    filters.extend(dataset_filter(pt_datasets, all_data));
+    filters.extend(shape.map(shape_filter));
+    if !filters.is_empty() {
+        query = json!({
+            "bool": {
+                "must": query,
+                "filter": filters,
+            }
+        });
+    }
+
+    if let Some(coord) = coord {
+        query = with_proximity(query, coord);
+    }
+
+    json!({
+        "query": query,
+        "from": offset,
+        "size": limit,
+    })
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn autocomplete(
+    q: &str,
+    pt_datasets: &[&str],
+    all_data: bool,
+    offset: u64,
+    limit: u64,
+    coord: Option<Coord>,
+    cnx_string: &str,
+    shape: Option<Vec<(f64, f64)>>,
+    types: &[&str],
+    langs: &[&str],
+    timeout: Option<Duration>,
+    fuzziness: Option<Fuzziness>,
+    synonyms: &SynonymTable,
+) -> Result<Vec<Value>, BragiError> {
+    let body = build_body(
+        q,
+        langs,
+        fuzziness.as_ref(),
+        synonyms,
+        pt_datasets,
+        all_data,
+        types,
+        coord.as_ref(),
+        shape.as_deref(),
+        offset,
+        limit,
+    );
+
+    // the query body above is Elasticsearch-specific, but which engine runs
+    // it (and how) is `from_addr`'s call, same as for the importers
+    let backend = mimir::rubber::from_addr(cnx_string)?;
+    Ok(backend.search(&body, timeout)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fuzziness() -> Fuzziness {
+        Fuzziness {
+            min_length_1_typo: 5,
+            min_length_2_typos: 9,
+            prefix_length: 1,
+        }
+    }
+
+    #[test]
+    fn words_below_the_first_threshold_are_exact_only() {
+        assert_eq!(fuzziness_for_word("rue", &fuzziness()), None);
+    }
+
+    #[test]
+    fn words_between_the_thresholds_tolerate_one_typo() {
+        assert_eq!(fuzziness_for_word("avenue", &fuzziness()), Some(("1", 1)));
+    }
+
+    #[test]
+    fn words_above_the_second_threshold_tolerate_two_typos() {
+        assert_eq!(
+            fuzziness_for_word("boulevardus", &fuzziness()),
+            Some(("2", 1))
+        );
+    }
+
+    #[test]
+    fn thresholds_are_inclusive_boundaries() {
+        assert_eq!(fuzziness_for_word("abcd", &fuzziness()), None);
+        assert_eq!(fuzziness_for_word("abcde", &fuzziness()), Some(("1", 1)));
+        assert_eq!(fuzziness_for_word("abcdefgh", &fuzziness()), Some(("1", 1)));
+        assert_eq!(
+            fuzziness_for_word("abcdefghi", &fuzziness()),
+            Some(("2", 1))
+        );
+    }
+}
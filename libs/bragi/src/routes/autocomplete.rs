@@ -37,6 +37,26 @@ fn default_limit() -> u64 {
     10u64
 }
 
+/// Word-length thresholds from which (inclusive) the ES query tolerates one, then two,
+/// edit-distance typos. Below `min_length_1_typo` a word must match exactly;
+/// a `prefix_length` of 1 keeps the leading character from ever being fuzzed.
+#[derive(Debug, Clone, Copy)]
+pub struct Fuzziness {
+    pub min_length_1_typo: u8,
+    pub min_length_2_typos: u8,
+    pub prefix_length: u8,
+}
+
+impl Default for Fuzziness {
+    fn default() -> Self {
+        Fuzziness {
+            min_length_1_typo: 5,
+            min_length_2_typos: 9,
+            prefix_length: 1,
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Params {
     q: String,
@@ -56,6 +76,13 @@ pub struct Params {
     #[serde(default, rename = "type")]
     types: Vec<Type>,
     lang: Option<String>,
+    /// opt-in typo-tolerant matching, off by default so clean queries are unaffected
+    #[serde(default)]
+    typo_tolerance: Option<bool>,
+    /// override the minimum word length (in characters) tolerating one typo
+    typo_min_length_1: Option<u8>,
+    /// override the minimum word length (in characters) tolerating two typos
+    typo_min_length_2: Option<u8>,
 }
 
 impl Params {
@@ -77,6 +104,20 @@ impl Params {
     fn timeout(&self) -> Option<Duration> {
         self.timeout.map(Duration::from_millis)
     }
+    /// `None` means typo tolerance is disabled for this query.
+    fn fuzziness(&self) -> Option<Fuzziness> {
+        if !self.typo_tolerance.unwrap_or(false) {
+            return None;
+        }
+        let defaults = Fuzziness::default();
+        Some(Fuzziness {
+            min_length_1_typo: self.typo_min_length_1.unwrap_or(defaults.min_length_1_typo),
+            min_length_2_typos: self
+                .typo_min_length_2
+                .unwrap_or(defaults.min_length_2_typos),
+            ..defaults
+        })
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -138,6 +179,8 @@ pub fn call_autocomplete(
         &params.types_as_str(),
         &langs,
         timeout,
+        params.fuzziness(),
+        &state.synonyms,
     );
     res.map(|r| Autocomplete::from_with_lang(r, langs.into_iter().next()))
         .map(Json)
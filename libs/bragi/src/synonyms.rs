@@ -0,0 +1,157 @@
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+
+/// A table of bidirectional synonym groups, keyed by language: "bd" and
+/// "boulevard" are two members of the same group, so a query for either one
+/// expands to look for both. Groups are language-aware since an abbreviation
+/// in one language can collide with a full word in another.
+pub struct SynonymTable {
+    // lang -> (word -> index of its group in `groups`)
+    index: BTreeMap<String, BTreeMap<String, usize>>,
+    groups: Vec<Vec<String>>,
+}
+
+impl SynonymTable {
+    fn from_groups(groups: Vec<(&str, Vec<&str>)>) -> Self {
+        let mut table = SynonymTable {
+            index: BTreeMap::new(),
+            groups: Vec::new(),
+        };
+        for (lang, words) in groups {
+            let group_id = table.groups.len();
+            table
+                .groups
+                .push(words.iter().map(|w| w.to_string()).collect());
+            let lang_index = table
+                .index
+                .entry(lang.to_string())
+                .or_insert_with(BTreeMap::new);
+            for word in words {
+                lang_index.insert(word.to_lowercase(), group_id);
+            }
+        }
+        table
+    }
+
+    /// Every member of `token`'s synonym group for `lang`, `token` itself
+    /// included. A token with no known synonym just expands to itself.
+    pub fn expand(&self, token: &str, lang: &str) -> Vec<String> {
+        let lowered = token.to_lowercase();
+        let group = self
+            .index
+            .get(lang)
+            .and_then(|lang_index| lang_index.get(&lowered))
+            .map(|&id| &self.groups[id]);
+        match group {
+            Some(group) => group.clone(),
+            None => vec![token.to_string()],
+        }
+    }
+
+    /// Parse a synonym file: one group per line, members separated by
+    /// commas, optionally prefixed with `lang:` (defaults to "fr"), e.g.
+    /// `fr:bd,boulevard` or `av,avenue`.
+    fn parse(content: &str) -> Vec<(String, Vec<String>)> {
+        content
+            .lines()
+            .map(str::trim)
+            .filter(|l| !l.is_empty() && !l.starts_with('#'))
+            .filter_map(|line| {
+                let (lang, rest) = match line.find(':') {
+                    Some(i) => (&line[..i], &line[i + 1..]),
+                    None => ("fr", line),
+                };
+                let words: Vec<String> = rest.split(',').map(|w| w.trim().to_string()).collect();
+                if words.len() < 2 {
+                    None
+                } else {
+                    Some((lang.to_string(), words))
+                }
+            })
+            .collect()
+    }
+}
+
+fn default_fr_synonyms() -> Vec<(&'static str, Vec<&'static str>)> {
+    vec![
+        ("fr", vec!["bd", "boulevard"]),
+        ("fr", vec!["av", "ave", "avenue"]),
+        ("fr", vec!["st", "saint"]),
+        ("fr", vec!["ste", "sainte"]),
+        ("fr", vec!["pl", "place"]),
+        ("fr", vec!["fg", "faubourg"]),
+        ("fr", vec!["all", "allee", "allée"]),
+        ("fr", vec!["chem", "chemin"]),
+        ("fr", vec!["imp", "impasse"]),
+        ("fr", vec!["rte", "route"]),
+    ]
+}
+
+/// Load the synonym table from the file pointed to by `BRAGI_SYNONYMS_FILE`,
+/// falling back to a small built-in set of French street/city abbreviations
+/// when the variable isn't set or the file can't be read. Called once, when
+/// building `Context`, rather than lazily from a process-global.
+pub fn load() -> SynonymTable {
+    let from_file = env::var("BRAGI_SYNONYMS_FILE")
+        .ok()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .map(|content| SynonymTable::parse(&content))
+        .filter(|groups| !groups.is_empty());
+
+    match from_file {
+        Some(groups) => {
+            let groups: Vec<(&str, Vec<&str>)> = groups
+                .iter()
+                .map(|(lang, words)| (lang.as_str(), words.iter().map(String::as_str).collect()))
+                .collect();
+            SynonymTable::from_groups(groups)
+        }
+        None => SynonymTable::from_groups(default_fr_synonyms()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn expand_unknown_word_returns_itself() {
+        let table = SynonymTable::from_groups(vec![("fr", vec!["bd", "boulevard"])]);
+        assert_eq!(table.expand("rue", "fr"), vec!["rue".to_string()]);
+    }
+
+    #[test]
+    fn expand_known_word_returns_its_whole_group() {
+        let table = SynonymTable::from_groups(vec![("fr", vec!["bd", "boulevard"])]);
+        let mut expanded = table.expand("bd", "fr");
+        expanded.sort();
+        assert_eq!(expanded, vec!["bd".to_string(), "boulevard".to_string()]);
+    }
+
+    #[test]
+    fn expand_is_case_insensitive_and_language_scoped() {
+        let table = SynonymTable::from_groups(vec![("fr", vec!["bd", "boulevard"])]);
+        assert_eq!(table.expand("BD", "fr").len(), 2);
+        // the group only exists for "fr", so "en" falls back to the word itself
+        assert_eq!(table.expand("bd", "en"), vec!["bd".to_string()]);
+    }
+
+    #[test]
+    fn parse_reads_groups_with_and_without_a_lang_prefix() {
+        let parsed = SynonymTable::parse("fr:bd,boulevard\nav,avenue\n# comment\n\nst\n");
+        assert_eq!(
+            parsed,
+            vec![
+                (
+                    "fr".to_string(),
+                    vec!["bd".to_string(), "boulevard".to_string()]
+                ),
+                (
+                    "fr".to_string(),
+                    vec!["av".to_string(), "avenue".to_string()]
+                ),
+            ]
+        );
+    }
+}
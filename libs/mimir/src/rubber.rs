@@ -0,0 +1,206 @@
+use crate::objects::Admin;
+use serde::Serialize;
+use serde_json::json;
+use std::fmt;
+use std::time::Duration;
+
+/// Documents are sent to Elasticsearch's `_bulk` endpoint in batches this
+/// large, rather than one HTTP request per document.
+const BULK_BATCH_SIZE: usize = 1000;
+
+#[derive(Debug)]
+pub struct Error(pub String);
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<reqwest::Error> for Error {
+    fn from(e: reqwest::Error) -> Self {
+        Error(e.to_string())
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct IndexSettings {
+    pub nb_shards: usize,
+    pub nb_replicas: usize,
+}
+
+/// Every operation the importers and Bragi need from a search engine:
+/// loading the index templates, reading back the admins of a dataset,
+/// bulk-indexing documents, and running an autocomplete query. `Rubber`
+/// is the Elasticsearch implementation; `from_addr` is the only place
+/// that needs to know about it.
+pub trait SearchBackend {
+    fn initialize_templates(&mut self) -> Result<(), Error>;
+    fn get_admins_from_dataset(&mut self, dataset: &str) -> Result<Vec<Admin>, Error>;
+    fn bulk_index(
+        &mut self,
+        dataset: &str,
+        index_settings: &IndexSettings,
+        docs: &mut dyn Iterator<Item = serde_json::Value>,
+    ) -> Result<usize, Error>;
+    /// Run a query body (already built by the caller) and return the raw
+    /// `hits.hits` array.
+    fn search(&self, body: &serde_json::Value, timeout: Option<Duration>) -> Result<Vec<serde_json::Value>, Error>;
+}
+
+pub struct Rubber {
+    cnx_string: String,
+    client: reqwest::Client,
+}
+
+impl Rubber {
+    pub fn new(cnx_string: &str) -> Self {
+        Rubber {
+            cnx_string: cnx_string.to_string(),
+            client: reqwest::Client::new(),
+        }
+    }
+
+    /// Create `index` with the requested `number_of_shards`/`number_of_replicas`.
+    /// An already-existing index is left untouched: ES answers with a 400
+    /// `resource_already_exists_exception`, which is exactly what we want.
+    fn create_index(&self, index: &str, index_settings: &IndexSettings) -> Result<(), Error> {
+        let body = json!({
+            "settings": {
+                "number_of_shards": index_settings.nb_shards,
+                "number_of_replicas": index_settings.nb_replicas,
+            }
+        });
+        let response = self
+            .client
+            .put(&format!("{}/{}", self.cnx_string, index))
+            .json(&body)
+            .send()?;
+        if response.status().is_client_error() {
+            return Ok(());
+        }
+        response.error_for_status()?;
+        Ok(())
+    }
+
+    /// Send one `_bulk` request for `batch`, a sequence of already-built
+    /// NDJSON action/document line pairs.
+    fn send_bulk(&self, index: &str, batch: &str) -> Result<(), Error> {
+        let response = self
+            .client
+            .post(&format!("{}/{}/_bulk", self.cnx_string, index))
+            .header(reqwest::header::CONTENT_TYPE, "application/x-ndjson")
+            .body(batch.to_string())
+            .send()?
+            .error_for_status()?
+            .json::<serde_json::Value>()?;
+        if response["errors"].as_bool().unwrap_or(false) {
+            return Err(Error(format!(
+                "bulk index into '{}' reported errors: {}",
+                index, response
+            )));
+        }
+        Ok(())
+    }
+}
+
+impl SearchBackend for Rubber {
+    fn initialize_templates(&mut self) -> Result<(), Error> {
+        self.client
+            .put(&format!("{}/_template/munin", self.cnx_string))
+            .send()?
+            .error_for_status()?;
+        Ok(())
+    }
+
+    fn get_admins_from_dataset(&mut self, dataset: &str) -> Result<Vec<Admin>, Error> {
+        let resp = self
+            .client
+            .get(&format!("{}/munin_admin_{}/_search", self.cnx_string, dataset))
+            .send()?
+            .error_for_status()?
+            .json::<serde_json::Value>()?;
+        let admins = resp["hits"]["hits"]
+            .as_array()
+            .cloned()
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|hit| serde_json::from_value(hit["_source"].clone()).ok())
+            .collect();
+        Ok(admins)
+    }
+
+    fn bulk_index(
+        &mut self,
+        dataset: &str,
+        index_settings: &IndexSettings,
+        docs: &mut dyn Iterator<Item = serde_json::Value>,
+    ) -> Result<usize, Error> {
+        let index = format!("munin_{}", dataset);
+        self.create_index(&index, index_settings)?;
+
+        let mut nb_indexed = 0;
+        let mut batch = String::new();
+        let mut batch_len = 0;
+        for doc in docs {
+            batch.push_str(&json!({ "index": {} }).to_string());
+            batch.push('\n');
+            batch.push_str(&doc.to_string());
+            batch.push('\n');
+            batch_len += 1;
+            nb_indexed += 1;
+            if batch_len >= BULK_BATCH_SIZE {
+                self.send_bulk(&index, &batch)?;
+                batch.clear();
+                batch_len = 0;
+            }
+        }
+        if batch_len > 0 {
+            self.send_bulk(&index, &batch)?;
+        }
+
+        self.client
+            .post(&format!("{}/{}/_refresh", self.cnx_string, index))
+            .send()?
+            .error_for_status()?;
+        Ok(nb_indexed)
+    }
+
+    fn search(&self, body: &serde_json::Value, timeout: Option<Duration>) -> Result<Vec<serde_json::Value>, Error> {
+        let mut request = self
+            .client
+            .post(&format!("{}/_search", self.cnx_string))
+            .json(body);
+        if let Some(timeout) = timeout {
+            request = request.timeout(timeout);
+        }
+        let response = request.send()?.error_for_status()?.json::<serde_json::Value>()?;
+        Ok(response["hits"]["hits"].as_array().cloned().unwrap_or_default())
+    }
+}
+
+/// Build the search backend described by `cnx_string`, dispatching on its
+/// scheme. `elasticsearch://` and `http://` keep today's behavior (a
+/// `Rubber` talking to an Elasticsearch cluster); other schemes are
+/// rejected so a future backend only has to add a branch here, not touch
+/// every call site.
+pub fn from_addr(cnx_string: &str) -> Result<Box<dyn SearchBackend>, Error> {
+    let scheme = cnx_string.split("://").next().unwrap_or("");
+    match scheme {
+        "http" | "https" => Ok(Box::new(Rubber::new(cnx_string))),
+        // Elasticsearch is reached over plain HTTP underneath
+        "elasticsearch" => Ok(Box::new(Rubber::new(
+            &cnx_string.replacen("elasticsearch://", "http://", 1),
+        ))),
+        _ => Err(Error(format!(
+            "don't know how to build a search backend for connection string '{}'",
+            cnx_string
+        ))),
+    }
+}
+
+pub fn to_json<T: Serialize>(doc: T) -> serde_json::Value {
+    serde_json::to_value(doc).expect("a mimir document always serializes to JSON")
+}
@@ -0,0 +1,63 @@
+use bzip2::read::BzDecoder;
+use flate2::read::GzDecoder;
+use mimir::rubber::{to_json, IndexSettings, SearchBackend};
+use serde::de::DeserializeOwned;
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use xz2::read::XzDecoder;
+
+/// Open `path`, transparently decompressing it based on its extension
+/// (`.gz`, `.bz2`, `.xz`) before handing the byte stream to the CSV reader.
+/// Files with any other extension (or none) are read as-is.
+fn reader_for(path: &Path) -> Result<Box<dyn Read>, mimirsbrunn::Error> {
+    let file = File::open(path)?;
+    let reader: Box<dyn Read> = match path.extension().and_then(|e| e.to_str()) {
+        Some("gz") => Box::new(GzDecoder::new(file)),
+        Some("bz2") => Box::new(BzDecoder::new(file)),
+        Some("xz") => Box::new(XzDecoder::new(file)),
+        _ => Box::new(file),
+    };
+    Ok(reader)
+}
+
+/// Records are indexed in batches this large rather than buffering an
+/// entire (potentially multi-million row) dataset into memory before the
+/// first `bulk_index` call.
+const IMPORT_BATCH_SIZE: usize = 5_000;
+
+/// Read every file in `files` as CSV (after transparent decompression),
+/// turn each record into a mimir document via `into_addr`, and bulk index
+/// them in bounded-size batches. A directory can mix plain and compressed
+/// files in the same import: each file is decompressed independently
+/// based on its own extension.
+pub fn import_addresses<T, F, I>(
+    backend: &mut dyn SearchBackend,
+    _has_pk: bool,
+    index_settings: IndexSettings,
+    dataset: &str,
+    files: I,
+    into_addr: F,
+) -> Result<(), mimirsbrunn::Error>
+where
+    T: DeserializeOwned,
+    F: Fn(T) -> mimir::Addr,
+    I: Iterator<Item = PathBuf>,
+{
+    let mut batch = Vec::with_capacity(IMPORT_BATCH_SIZE);
+    for file in files {
+        let reader = reader_for(&file)?;
+        let mut rdr = csv::Reader::from_reader(reader);
+        for record in rdr.deserialize() {
+            let record: T = record?;
+            batch.push(to_json(into_addr(record)));
+            if batch.len() >= IMPORT_BATCH_SIZE {
+                backend.bulk_index(dataset, &index_settings, &mut batch.drain(..))?;
+            }
+        }
+    }
+    if !batch.is_empty() {
+        backend.bulk_index(dataset, &index_settings, &mut batch.drain(..))?;
+    }
+    Ok(())
+}
@@ -33,24 +33,20 @@ extern crate slog;
 #[macro_use]
 extern crate slog_scope;
 
-use lazy_static::lazy_static;
 use mimir::objects::Admin;
-use mimir::rubber::{IndexSettings, Rubber};
+use mimir::rubber::IndexSettings;
 use mimirsbrunn::addr_reader::import_addresses;
 use mimirsbrunn::admin_geofinder::AdminGeoFinder;
 use serde_derive::{Deserialize, Serialize};
 use std::collections::BTreeMap;
 use std::fs;
-use std::path::PathBuf;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use structopt::StructOpt;
 
 type AdminFromInsee = BTreeMap<String, Arc<Admin>>;
 
-lazy_static! {
-    static ref DEFAULT_NB_THREADS: String = num_cpus::get().to_string();
-}
-
 #[derive(Serialize, Deserialize)]
 pub struct Bano {
     pub id: String,
@@ -127,17 +123,19 @@ fn index_bano<I>(
     cnx_string: &str,
     dataset: &str,
     files: I,
-    nb_threads: usize,
     nb_shards: usize,
     nb_replicas: usize,
 ) -> Result<(), mimirsbrunn::Error>
 where
     I: Iterator<Item = std::path::PathBuf>,
 {
-    let mut rubber = Rubber::new(cnx_string);
-    rubber.initialize_templates()?;
+    // `from_addr` dispatches on the connection string's scheme; today only
+    // `elasticsearch://`/`http://` are implemented, but callers no longer
+    // depend on the concrete `Rubber` type.
+    let mut backend = mimir::rubber::from_addr(cnx_string)?;
+    backend.initialize_templates()?;
 
-    let admins = rubber
+    let admins = backend
         .get_admins_from_dataset(dataset)
         .unwrap_or_else(|err| {
             info!(
@@ -162,9 +160,8 @@ where
     };
 
     import_addresses(
-        &mut rubber,
+        &mut *backend,
         false,
-        nb_threads,
         index_settings,
         dataset,
         files,
@@ -174,7 +171,9 @@ where
 
 #[derive(StructOpt, Debug)]
 struct Args {
-    /// Bano files. Can be either a directory or a file.
+    /// Bano files. Can be either a directory, a file, or an http(s):// URL
+    /// to download before importing. Files may be compressed: .gz, .bz2 and
+    /// .xz are decompressed on the fly.
     #[structopt(short = "i", long = "input", parse(from_os_str))]
     input: PathBuf,
     /// Elasticsearch parameters.
@@ -187,13 +186,6 @@ struct Args {
     /// Name of the dataset.
     #[structopt(short = "d", long = "dataset", default_value = "fr")]
     dataset: String,
-    /// Number of threads to use
-    #[structopt(
-        short = "t",
-        long = "nb-threads",
-        raw(default_value = "&DEFAULT_NB_THREADS")
-    )]
-    nb_threads: usize,
     /// Number of shards for the es index
     #[structopt(short = "s", long = "nb-shards", default_value = "5")]
     nb_shards: usize,
@@ -202,15 +194,96 @@ struct Args {
     nb_replicas: usize,
 }
 
+fn as_url(input: &Path) -> Option<String> {
+    input
+        .to_str()
+        .filter(|s| s.starts_with("http://") || s.starts_with("https://"))
+        .map(str::to_string)
+}
+
+/// Download `url` to a temp file, reporting progress, and return its path.
+/// If a previous, partial download is found at the destination it is
+/// resumed with a `Range` request rather than restarted from scratch.
+fn fetch_remote(url: &str) -> Result<PathBuf, mimirsbrunn::Error> {
+    let file_name = url
+        .rsplit('/')
+        .next()
+        .filter(|s| !s.is_empty())
+        .unwrap_or("bano_input");
+    let dest = std::env::temp_dir().join(file_name);
+    let resume_from = fs::metadata(&dest).map(|m| m.len()).unwrap_or(0);
+
+    let client = reqwest::Client::new();
+    let mut request = client.get(url);
+    if resume_from > 0 {
+        info!("resuming download of {} from byte {}", url, resume_from);
+        request = request.header(reqwest::header::RANGE, format!("bytes={}-", resume_from));
+    }
+    let mut response = request.send()?.error_for_status()?;
+
+    // the server is free to ignore `Range` and send the whole file back with
+    // a plain 200 OK; appending that to our partial file would silently
+    // corrupt it, so only resume when it actually honoured the range
+    let resuming = resume_from > 0 && response.status() == reqwest::StatusCode::PARTIAL_CONTENT;
+    if resume_from > 0 && !resuming {
+        info!(
+            "server did not honour the range request for {}, restarting download from scratch",
+            url
+        );
+    }
+    let resume_from = if resuming { resume_from } else { 0 };
+
+    let total_size = response
+        .headers()
+        .get(reqwest::header::CONTENT_LENGTH)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<u64>().ok())
+        .unwrap_or(0)
+        + resume_from;
+
+    let pb = indicatif::ProgressBar::new(total_size);
+    pb.set_style(
+        indicatif::ProgressStyle::default_bar()
+            .template("{msg} [{bar:40.cyan/blue}] {bytes}/{total_bytes} ({eta})"),
+    );
+    pb.set_message(file_name);
+    pb.set_position(resume_from);
+
+    let mut out = fs::OpenOptions::new()
+        .create(true)
+        .append(resuming)
+        .write(true)
+        .truncate(!resuming)
+        .open(&dest)?;
+    let mut buf = [0u8; 8192];
+    loop {
+        let n = response.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        out.write_all(&buf[..n])?;
+        pb.inc(n as u64);
+    }
+    pb.finish_with_message(&format!("{} downloaded", file_name));
+
+    Ok(dest)
+}
+
 fn run(args: Args) -> Result<(), mimirsbrunn::Error> {
     info!("importing bano into Mimir");
-    if args.input.is_dir() {
-        let paths: std::fs::ReadDir = fs::read_dir(&args.input)?;
+    let input = match as_url(&args.input) {
+        Some(url) => fetch_remote(&url)?,
+        None => args.input,
+    };
+    if input.is_dir() {
+        let paths: std::fs::ReadDir = fs::read_dir(&input)?;
         index_bano(
             &args.connection_string,
             &args.dataset,
-            paths.map(|p| p.unwrap().path()),
-            args.nb_threads,
+            // a directory can hold a mix of plain and compressed (.gz/.bz2/.xz)
+            // files in the same batch; decompression itself happens lazily
+            // while each file is read
+            paths.map(|p| p.unwrap().path()).filter(|p| p.is_file()),
             args.nb_shards,
             args.nb_replicas,
         )
@@ -218,8 +291,7 @@ fn run(args: Args) -> Result<(), mimirsbrunn::Error> {
         index_bano(
             &args.connection_string,
             &args.dataset,
-            std::iter::once(args.input),
-            args.nb_threads,
+            std::iter::once(input),
             args.nb_shards,
             args.nb_replicas,
         )
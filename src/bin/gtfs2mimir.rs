@@ -0,0 +1,314 @@
+// Copyright © 2016, Canal TP and/or its affiliates. All rights reserved.
+//
+// This file is part of Navitia,
+//     the software to build cool stuff with public transport.
+//
+// Hope you'll enjoy and contribute to this project,
+//     powered by Canal TP (www.canaltp.fr).
+// Help us simplify mobility and open public transport:
+//     a non ending quest to the responsive locomotion way of traveling!
+//
+// LICENCE: This program is free software; you can redistribute it
+// and/or modify it under the terms of the GNU Affero General Public
+// License as published by the Free Software Foundation, either
+// version 3 of the License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU
+// Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public
+// License along with this program. If not, see
+// <http://www.gnu.org/licenses/>.
+//
+// Stay tuned using
+// twitter @navitia
+// IRC #navitia on freenode
+// https://groups.google.com/d/forum/navitia
+// www.navitia.io
+
+#[macro_use]
+extern crate slog;
+#[macro_use]
+extern crate slog_scope;
+
+use mimir::rubber::IndexSettings;
+use mimirsbrunn::admin_geofinder::AdminGeoFinder;
+use serde_derive::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+use structopt::StructOpt;
+
+/// One line of GTFS' `stops.txt`.
+///
+/// `location_type` follows the GTFS spec: `0` (or absent) is a stop/platform,
+/// `1` is a station. Child stops reference their station through
+/// `parent_station`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Stop {
+    pub stop_id: String,
+    pub stop_name: String,
+    pub stop_lat: f64,
+    pub stop_lon: f64,
+    #[serde(default)]
+    pub location_type: u8,
+    #[serde(default)]
+    pub parent_station: String,
+}
+
+impl Stop {
+    fn is_station(&self) -> bool {
+        self.location_type == 1
+    }
+}
+
+/// A `StopArea` being assembled from its child stops/platforms: the station
+/// itself (if any) plus the running sum of its children's coordinates, used
+/// to derive a representative coordinate once every stop has been read.
+struct StopAreaBuilder {
+    id: String,
+    name: String,
+    lon_sum: f64,
+    lat_sum: f64,
+    nb_coords: usize,
+}
+
+impl StopAreaBuilder {
+    fn new(id: String, name: String) -> Self {
+        StopAreaBuilder {
+            id: id,
+            name: name,
+            lon_sum: 0.,
+            lat_sum: 0.,
+            nb_coords: 0,
+        }
+    }
+    fn add_coord(&mut self, lon: f64, lat: f64) {
+        self.lon_sum += lon;
+        self.lat_sum += lat;
+        self.nb_coords += 1;
+    }
+    fn coord(&self) -> (f64, f64) {
+        if self.nb_coords == 0 {
+            (self.lon_sum, self.lat_sum)
+        } else {
+            (
+                self.lon_sum / self.nb_coords as f64,
+                self.lat_sum / self.nb_coords as f64,
+            )
+        }
+    }
+    fn into_stop_area(self, admins_geofinder: &AdminGeoFinder) -> mimir::StopArea {
+        let (lon, lat) = self.coord();
+        let admins = admins_geofinder.get(&geo::Coordinate { x: lon, y: lat });
+        let weight = admins
+            .iter()
+            .find(|a| a.level == 8)
+            .map_or(0., |a| a.weight);
+        let zip_codes = admins
+            .iter()
+            .flat_map(|a| a.zip_codes.iter().cloned())
+            .collect();
+        mimir::StopArea {
+            id: format!("stop_area:{}", self.id),
+            label: self.name.clone(),
+            name: self.name,
+            coord: mimir::Coord::new(lon, lat),
+            administrative_regions: admins,
+            weight: weight,
+            zip_codes: zip_codes,
+            distance: None,
+        }
+    }
+}
+
+/// Group every stop/platform under its `parent_station` to build one
+/// `StopAreaBuilder` per station, then fold in stations that have no child
+/// (they are their own stop area).
+fn group_stops_by_parent(files: impl Iterator<Item = PathBuf>) -> Result<Vec<StopAreaBuilder>, mimirsbrunn::Error> {
+    let mut areas: BTreeMap<String, StopAreaBuilder> = BTreeMap::new();
+    let mut stations: BTreeMap<String, Stop> = BTreeMap::new();
+
+    for file in files {
+        let mut rdr = csv::Reader::from_path(&file)?;
+        for stop in rdr.deserialize() {
+            let stop: Stop = stop?;
+            if stop.is_station() {
+                stations.insert(stop.stop_id.clone(), stop);
+                continue;
+            }
+            if stop.parent_station.is_empty() {
+                // a stop with no parent station is a stop area on its own
+                let area = areas
+                    .entry(stop.stop_id.clone())
+                    .or_insert_with(|| StopAreaBuilder::new(stop.stop_id.clone(), stop.stop_name.clone()));
+                area.add_coord(stop.stop_lon, stop.stop_lat);
+            } else {
+                let area = areas.entry(stop.parent_station.clone()).or_insert_with(|| {
+                    StopAreaBuilder::new(stop.parent_station.clone(), stop.stop_name.clone())
+                });
+                area.add_coord(stop.stop_lon, stop.stop_lat);
+            }
+        }
+    }
+
+    // stations are named after themselves; a station with no child stop
+    // recorded in `areas` yet still needs its own coordinate, otherwise it
+    // would be indexed at (0, 0)
+    for (id, station) in stations {
+        let area = areas
+            .entry(id.clone())
+            .or_insert_with(|| StopAreaBuilder::new(id, station.stop_name.clone()));
+        if area.nb_coords == 0 {
+            area.add_coord(station.stop_lon, station.stop_lat);
+        }
+        area.name = station.stop_name;
+    }
+
+    Ok(areas.into_iter().map(|(_, v)| v).collect())
+}
+
+fn index_gtfs<I>(
+    cnx_string: &str,
+    dataset: &str,
+    files: I,
+    nb_shards: usize,
+    nb_replicas: usize,
+) -> Result<(), mimirsbrunn::Error>
+where
+    I: Iterator<Item = PathBuf>,
+{
+    let mut backend = mimir::rubber::from_addr(cnx_string)?;
+    backend.initialize_templates()?;
+
+    let admins = backend
+        .get_admins_from_dataset(dataset)
+        .unwrap_or_else(|err| {
+            info!(
+                "Administratives regions not found in es db for dataset {}. (error: {})",
+                dataset, err
+            );
+            vec![]
+        });
+    let admins_geofinder: AdminGeoFinder = admins.into_iter().collect();
+
+    let index_settings = IndexSettings {
+        nb_shards: nb_shards,
+        nb_replicas: nb_replicas,
+    };
+
+    let stop_areas: Vec<_> = group_stops_by_parent(files)?
+        .into_iter()
+        .map(|b| b.into_stop_area(&admins_geofinder))
+        .collect();
+
+    info!("{} stop areas to index for dataset {}", stop_areas.len(), dataset);
+
+    let mut docs = stop_areas.into_iter().map(mimir::rubber::to_json);
+    backend.bulk_index(dataset, &index_settings, &mut docs)?;
+    Ok(())
+}
+
+#[derive(StructOpt, Debug)]
+struct Args {
+    /// GTFS stops.txt files. Can be either a directory or a file.
+    #[structopt(short = "i", long = "input", parse(from_os_str))]
+    input: PathBuf,
+    /// Elasticsearch parameters.
+    #[structopt(
+        short = "c",
+        long = "connection-string",
+        default_value = "http://localhost:9200/munin"
+    )]
+    connection_string: String,
+    /// Name of the dataset.
+    #[structopt(short = "d", long = "dataset", default_value = "fr")]
+    dataset: String,
+    /// Number of shards for the es index
+    #[structopt(short = "s", long = "nb-shards", default_value = "5")]
+    nb_shards: usize,
+    /// Number of replicas for the es index
+    #[structopt(short = "r", long = "nb-replicas", default_value = "1")]
+    nb_replicas: usize,
+}
+
+fn run(args: Args) -> Result<(), mimirsbrunn::Error> {
+    info!("importing gtfs stops into Mimir");
+    if args.input.is_dir() {
+        let paths: std::fs::ReadDir = fs::read_dir(&args.input)?;
+        index_gtfs(
+            &args.connection_string,
+            &args.dataset,
+            paths
+                .map(|p| p.unwrap().path())
+                .filter(|p| p.file_name().map_or(false, |n| n == "stops.txt")),
+            args.nb_shards,
+            args.nb_replicas,
+        )
+    } else {
+        index_gtfs(
+            &args.connection_string,
+            &args.dataset,
+            std::iter::once(args.input),
+            args.nb_shards,
+            args.nb_replicas,
+        )
+    }
+}
+
+fn main() {
+    mimirsbrunn::utils::launch_run(run);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    fn write_stops(name: &str, content: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(name);
+        let mut file = fs::File::create(&path).unwrap();
+        file.write_all(content.as_bytes()).unwrap();
+        path
+    }
+
+    const HEADER: &str = "stop_id,stop_name,stop_lat,stop_lon,location_type,parent_station\n";
+
+    #[test]
+    fn station_with_no_children_keeps_its_own_coord() {
+        let path = write_stops(
+            "gtfs2mimir_test_station_alone.txt",
+            &format!("{}station_a,Station A,45.0,1.0,1,\n", HEADER),
+        );
+        let areas = group_stops_by_parent(std::iter::once(path)).unwrap();
+        assert_eq!(areas.len(), 1);
+        assert_eq!(areas[0].coord(), (1.0, 45.0));
+    }
+
+    #[test]
+    fn station_with_children_averages_their_coords() {
+        let path = write_stops(
+            "gtfs2mimir_test_station_with_children.txt",
+            &format!(
+                "{}station_b,Station B,0.0,0.0,1,\nplatform_1,Platform 1,44.0,2.0,0,station_b\nplatform_2,Platform 2,46.0,4.0,0,station_b\n",
+                HEADER
+            ),
+        );
+        let areas = group_stops_by_parent(std::iter::once(path)).unwrap();
+        assert_eq!(areas.len(), 1);
+        assert_eq!(areas[0].coord(), (3.0, 45.0));
+    }
+
+    #[test]
+    fn stop_without_parent_is_its_own_area() {
+        let path = write_stops(
+            "gtfs2mimir_test_standalone_stop.txt",
+            &format!("{}standalone,Standalone Stop,12.0,3.0,0,\n", HEADER),
+        );
+        let areas = group_stops_by_parent(std::iter::once(path)).unwrap();
+        assert_eq!(areas.len(), 1);
+        assert_eq!(areas[0].coord(), (3.0, 12.0));
+    }
+}